@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use hyper::body::Bytes;
+use hyper::Uri;
+use serde::{Deserialize, Serialize};
+
+/// The last good body of each HTTP subscription, kept so that unchanged
+/// subscriptions are not downloaded again and an unreachable provider does
+/// not leave us without servers.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// Response headers stored next to a cached body.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Meta {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub userinfo: Option<String>,
+}
+
+impl Cache {
+    /// Uses `dir`, falling back to `$XDG_CACHE_HOME/clash_profile` or
+    /// `~/.cache/clash_profile`.
+    pub fn new(dir: Option<PathBuf>) -> Cache {
+        let dir = dir
+            .or_else(|| {
+                env::var_os("XDG_CACHE_HOME").map(|d| PathBuf::from(d).join("clash_profile"))
+            })
+            .or_else(|| env::var_os("HOME").map(|d| PathBuf::from(d).join(".cache/clash_profile")))
+            .unwrap_or_else(|| PathBuf::from(".cache"));
+        Cache { dir }
+    }
+
+    /// Loads the cached copy of a subscription, if there is a readable one.
+    pub fn load(&self, uri: &Uri) -> Option<(Meta, Bytes)> {
+        let (meta_path, body_path) = self.paths(uri);
+        let meta = serde_json::from_slice(&fs::read(meta_path).ok()?).ok()?;
+        let body = fs::read(body_path).ok()?;
+        Some((meta, body.into()))
+    }
+
+    pub fn store(&self, uri: &Uri, meta: &Meta, body: &[u8]) -> io::Result<()> {
+        let (meta_path, body_path) = self.paths(uri);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(body_path, body)?;
+        fs::write(meta_path, serde_json::to_vec(meta)?)
+    }
+
+    /// Files are named after the host, to be recognizable, and a hash of the
+    /// whole URI, so that a subscription pointed at another URI does not
+    /// revalidate or fall back to the old one's copy.
+    fn paths(&self, uri: &Uri) -> (PathBuf, PathBuf) {
+        let host: String = uri
+            .host()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' => c,
+                _ => '_',
+            })
+            .collect();
+        let file = format!("{}-{:016x}", host, fnv1a(uri.to_string().as_bytes()));
+        (
+            self.dir.join(format!("{}.json", file)),
+            self.dir.join(format!("{}.body", file)),
+        )
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is the same on every build, so
+/// the cache survives upgrades.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,73 @@
+use std::collections::HashSet;
+
+use crate::Group;
+
+/// Policies Clash provides without them being defined.
+const BUILTIN_POLICIES: [&str; 5] = ["DIRECT", "REJECT", "REJECT-DROP", "PASS", "COMPATIBLE"];
+
+/// Checks that every proxy group member and every rule target is a group, a
+/// proxy or a built-in policy. Returns a description of each problem found.
+pub fn check(groups: &[Group], proxies: &[String], rules: &str) -> Vec<String> {
+    let mut known: HashSet<&str> = BUILTIN_POLICIES.into_iter().collect();
+    known.extend(groups.iter().map(|g| g.name.as_str()));
+    known.extend(proxies.iter().map(String::as_str));
+    let mut problems = vec![];
+    for group in groups {
+        for member in &group.proxies {
+            if !known.contains(member.as_str()) {
+                problems.push(format!(
+                    "group {}: no proxy or group named {}",
+                    group.name, member
+                ));
+            }
+        }
+    }
+    for (n, line) in rules.lines().enumerate() {
+        let rule = match parse_rule(line) {
+            Some(rule) => rule,
+            None => continue,
+        };
+        match policy(rule) {
+            Some(policy) if known.contains(policy) => {}
+            Some(policy) => problems.push(format!(
+                "rules line {}: no proxy or group named {} in {}",
+                n + 1,
+                policy,
+                rule
+            )),
+            None => problems.push(format!("rules line {}: malformed rule {}", n + 1, rule)),
+        }
+    }
+    problems
+}
+
+/// Extracts the rule from a line of the rules file, a YAML list item such as
+/// `    - 'DOMAIN-SUFFIX,google.com,Choice'`. Blank and comment lines have
+/// none.
+fn parse_rule(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let rule = line.strip_prefix('-').unwrap_or(line).trim();
+    let rule = rule
+        .strip_prefix('\'')
+        .and_then(|r| r.strip_suffix('\''))
+        .or_else(|| rule.strip_prefix('"').and_then(|r| r.strip_suffix('"')))
+        .unwrap_or(rule);
+    Some(rule)
+}
+
+/// The proxy or group a rule sends matching traffic to.
+fn policy(rule: &str) -> Option<&str> {
+    let (kind, rest) = rule.split_once(',')?;
+    let rest = match kind {
+        // the payload of logic rules is a parenthesized list of rules
+        "AND" | "OR" | "NOT" => rest.get(rest.rfind(')')? + 1..)?.strip_prefix(',')?,
+        // MATCH has no payload, though it is sometimes written as an empty one
+        "MATCH" => rest.strip_prefix(',').unwrap_or(rest),
+        _ => rest.split_once(',')?.1,
+    };
+    let policy = rest.split(',').next()?.trim();
+    Some(policy).filter(|p| !p.is_empty())
+}
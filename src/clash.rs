@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::skipped::Skipped;
+use crate::{
+    number_or_string, required, Http, Hysteria2, Network, Plugin, Reality, Reserved, Server,
+    ShadowSocks, Socks5, Ssr, Trojan, Tuic, Vless, Vmess, WireGuard,
+};
+
+/// The part of a Clash profile we read: its `proxies` list. Proxies are read
+/// one by one, so that one we cannot read does not spoil the others.
+#[derive(Debug, Deserialize)]
+struct Profile {
+    #[serde(default)]
+    proxies: Vec<serde_yaml::Value>,
+}
+
+/// One entry of a Clash `proxies` list. Fields that only some proxy types
+/// carry are optional.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Proxy {
+    #[serde(deserialize_with = "string")]
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    server: String,
+    #[serde(deserialize_with = "number_or_string")]
+    port: u16,
+    cipher: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    password: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    uuid: Option<String>,
+    #[serde(rename = "alterId", default)]
+    alter_id: u32,
+    #[serde(default, deserialize_with = "optional_string")]
+    username: Option<String>,
+    #[serde(default)]
+    tls: bool,
+    #[serde(default, deserialize_with = "optional_string")]
+    sni: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    servername: Option<String>,
+    #[serde(default)]
+    skip_cert_verify: bool,
+    #[serde(default)]
+    alpn: Vec<String>,
+    client_fingerprint: Option<String>,
+    flow: Option<String>,
+    reality_opts: Option<RealityOpts>,
+    network: Option<String>,
+    ws_opts: Option<WsOpts>,
+    h2_opts: Option<H2Opts>,
+    http_opts: Option<HttpOpts>,
+    grpc_opts: Option<GrpcOpts>,
+    plugin: Option<String>,
+    #[serde(default)]
+    plugin_opts: PluginOpts,
+    obfs: Option<String>,
+    protocol: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    obfs_param: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    protocol_param: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    obfs_password: Option<String>,
+    #[serde(default, deserialize_with = "bandwidth")]
+    up: Option<String>,
+    #[serde(default, deserialize_with = "bandwidth")]
+    down: Option<String>,
+    congestion_controller: Option<String>,
+    udp_relay_mode: Option<String>,
+    #[serde(default)]
+    disable_sni: bool,
+    #[serde(default, deserialize_with = "optional_string")]
+    private_key: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    public_key: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    pre_shared_key: Option<String>,
+    ip: Option<String>,
+    ipv6: Option<String>,
+    mtu: Option<u16>,
+    reserved: Option<Reserved>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RealityOpts {
+    #[serde(deserialize_with = "string")]
+    public_key: String,
+    #[serde(default, deserialize_with = "optional_string")]
+    short_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsOpts {
+    #[serde(default, deserialize_with = "optional_string")]
+    path: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct H2Opts {
+    #[serde(default, deserialize_with = "optional_string")]
+    path: Option<String>,
+    #[serde(default)]
+    host: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpOpts {
+    #[serde(default)]
+    path: Vec<String>,
+    #[serde(default)]
+    headers: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct GrpcOpts {
+    #[serde(default, deserialize_with = "optional_string")]
+    grpc_service_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PluginOpts {
+    mode: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    host: Option<String>,
+    #[serde(default, deserialize_with = "optional_string")]
+    path: Option<String>,
+    #[serde(default)]
+    tls: bool,
+    #[serde(default)]
+    mux: bool,
+    #[serde(default, deserialize_with = "optional_string")]
+    password: Option<String>,
+    version: Option<u8>,
+}
+
+/// Bandwidths are written both as bare Mbps numbers and as strings such as
+/// `30 Mbps`.
+fn bandwidth<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    number_or_string(deserializer).map(|b: String| Some(b).filter(|b| !b.is_empty()))
+}
+
+/// YAML reads unquoted values such as `password: 12345678` as numbers or
+/// booleans, so accept any scalar where a string is expected.
+fn string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        String(String),
+        Int(i64),
+        UInt(u64),
+        Float(f64),
+        Bool(bool),
+    }
+    Ok(match Scalar::deserialize(deserializer)? {
+        Scalar::String(s) => s,
+        Scalar::Int(n) => n.to_string(),
+        Scalar::UInt(n) => n.to_string(),
+        Scalar::Float(n) => n.to_string(),
+        Scalar::Bool(b) => b.to_string(),
+    })
+}
+
+fn optional_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Scalar(#[serde(deserialize_with = "string")] String);
+    Option::<Scalar>::deserialize(deserializer).map(|s| s.map(|Scalar(s)| s))
+}
+
+/// Tells whether a subscription body is a Clash profile rather than a list of
+/// share links.
+pub fn is_profile(body: &str) -> bool {
+    body.lines().any(|l| l.starts_with("proxies:"))
+}
+
+/// Reads the proxies of a Clash profile. Proxies of a type we do not support,
+/// such as `snell`, or that lack a field are returned as skipped.
+pub fn parse_profile(source: &str, body: &str) -> Result<(Vec<Server>, Vec<Skipped>), String> {
+    let profile: Profile =
+        serde_yaml::from_str(body).map_err(|e| format!("invalid clash profile: {}", e))?;
+    let mut servers = vec![];
+    let mut skipped = vec![];
+    for (i, proxy) in profile.proxies.into_iter().enumerate() {
+        let field = |key| String::from(proxy.get(key).and_then(|v| v.as_str()).unwrap_or_default());
+        let (kind, name) = (field("type"), field("name"));
+        let server = serde_yaml::from_value::<Proxy>(proxy)
+            .map_err(|e| e.to_string())
+            .and_then(Server::try_from);
+        match server {
+            Ok(server) => servers.push(server),
+            Err(reason) => {
+                skipped.push(Skipped::entry(source, "proxy", i + 1, &kind, &name, reason))
+            }
+        }
+    }
+    Ok((servers, skipped))
+}
+
+impl Proxy {
+    fn network(&mut self) -> Option<Network> {
+        match self.network.as_deref() {
+            Some("ws") => {
+                let opts = self.ws_opts.take();
+                Some(Network::Ws {
+                    path: opts.as_ref().and_then(|o| o.path.clone()),
+                    host: opts.and_then(|mut o| o.headers.remove("Host")),
+                })
+            }
+            Some("h2") => {
+                let opts = self.h2_opts.take();
+                Some(Network::H2 {
+                    path: opts.as_ref().and_then(|o| o.path.clone()),
+                    host: opts.map_or_else(Vec::new, |o| o.host),
+                })
+            }
+            Some("http") => {
+                let opts = self.http_opts.take();
+                Some(Network::Http {
+                    path: opts.as_ref().and_then(|o| o.path.first().cloned()),
+                    host: opts.and_then(|mut o| o.headers.remove("Host")?.into_iter().next()),
+                })
+            }
+            Some("grpc") => Some(Network::Grpc {
+                service_name: self.grpc_opts.take().and_then(|o| o.grpc_service_name),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<Proxy> for Server {
+    type Error = String;
+
+    fn try_from(mut p: Proxy) -> Result<Self, Self::Error> {
+        let network = p.network();
+        match p.kind.as_str() {
+            "ss" => {
+                let opts = p.plugin_opts;
+                let plugin = match p.plugin.as_deref() {
+                    None => None,
+                    Some("obfs") => Some(Plugin::Obfs {
+                        mode: opts.mode.unwrap_or_else(|| String::from("http")),
+                        host: opts.host,
+                    }),
+                    Some("v2ray-plugin") => Some(Plugin::V2ray {
+                        mode: opts.mode.unwrap_or_else(|| String::from("websocket")),
+                        host: opts.host,
+                        path: opts.path,
+                        tls: opts.tls,
+                        mux: opts.mux,
+                    }),
+                    Some("shadow-tls") => Some(Plugin::ShadowTls {
+                        host: required(opts.host, "shadow-tls host")?,
+                        password: opts.password.unwrap_or_default(),
+                        version: opts.version.unwrap_or(2),
+                    }),
+                    Some(plugin) => return Err(format!("unsupported plugin {}", plugin)),
+                };
+                Ok(Server::SS(ShadowSocks {
+                    name: p.name,
+                    host: p.server,
+                    port: p.port,
+                    cipher: required(p.cipher, "cipher")?,
+                    password: required(p.password, "password")?,
+                    udp: true,
+                    plugin,
+                }))
+            }
+            "ssr" => Ok(Server::Ssr(Ssr {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                protocol: required(p.protocol, "protocol")?,
+                cipher: required(p.cipher, "cipher")?,
+                obfs: required(p.obfs, "obfs")?,
+                password: required(p.password, "password")?,
+                obfs_param: p.obfs_param,
+                protocol_param: p.protocol_param,
+            })),
+            "vmess" => {
+                let mut vmess = Vmess {
+                    name: p.name,
+                    host: p.server,
+                    port: p.port,
+                    uuid: required(p.uuid, "uuid")?,
+                    alter_id: p.alter_id,
+                    cipher: p.cipher.unwrap_or_default(),
+                    tls: String::from(if p.tls { "tls" } else { "" }),
+                    sni: p.servername.unwrap_or_default(),
+                    alpn: p.alpn.join(","),
+                    fingerprint: p.client_fingerprint.unwrap_or_default(),
+                    ..Default::default()
+                };
+                vmess.set_network(network);
+                Ok(Server::Vmess(vmess))
+            }
+            "trojan" => Ok(Server::Trojan(Trojan {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                password: required(p.password, "password")?,
+                sni: p.sni,
+                skip_cert_verify: p.skip_cert_verify,
+                alpn: p.alpn,
+                network,
+            })),
+            "vless" => Ok(Server::Vless(Vless {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                uuid: required(p.uuid, "uuid")?,
+                flow: p.flow,
+                tls: p.tls,
+                servername: p.servername,
+                skip_cert_verify: p.skip_cert_verify,
+                alpn: p.alpn,
+                client_fingerprint: p.client_fingerprint,
+                reality: p.reality_opts.map(|r| Reality {
+                    public_key: r.public_key,
+                    short_id: r.short_id,
+                }),
+                network,
+            })),
+            "hysteria2" => Ok(Server::Hysteria2(Hysteria2 {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                password: required(p.password, "password")?,
+                sni: p.sni,
+                skip_cert_verify: p.skip_cert_verify,
+                obfs: p.obfs,
+                obfs_password: p.obfs_password,
+                up: p.up,
+                down: p.down,
+                alpn: p.alpn,
+            })),
+            "tuic" => Ok(Server::Tuic(Tuic {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                uuid: required(p.uuid, "uuid")?,
+                password: required(p.password, "password")?,
+                sni: p.sni,
+                skip_cert_verify: p.skip_cert_verify,
+                disable_sni: p.disable_sni,
+                alpn: p.alpn,
+                congestion_controller: p.congestion_controller,
+                udp_relay_mode: p.udp_relay_mode,
+            })),
+            "socks5" => Ok(Server::Socks5(Socks5 {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                username: p.username,
+                password: p.password,
+                tls: p.tls,
+            })),
+            "http" => Ok(Server::Http(Http {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                username: p.username,
+                password: p.password,
+                tls: p.tls,
+            })),
+            "wireguard" => Ok(Server::WireGuard(WireGuard {
+                name: p.name,
+                host: p.server,
+                port: p.port,
+                private_key: required(p.private_key, "private-key")?,
+                public_key: required(p.public_key, "public-key")?,
+                pre_shared_key: p.pre_shared_key,
+                ip: p.ip,
+                ipv6: p.ipv6,
+                mtu: p.mtu,
+                reserved: p.reserved.map(Reserved::into_string),
+            })),
+            kind => Err(format!("unsupported clash proxy type {}", kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxies() {
+        let profile = "\
+proxies:
+  - { name: hk, type: trojan, server: a.example, port: 443, password: pw, sni: s.example, network: ws, ws-opts: { path: /ws } }
+  - { name: jp, type: ss, server: b.example, port: 8388, cipher: aes-256-gcm, password: pw, plugin: obfs, plugin-opts: { mode: tls, host: q.example } }
+  - { name: wg, type: wireguard, server: c.example, port: 51820, private-key: priv, public-key: pub, reserved: [1, 2, 3] }
+";
+        let (servers, skipped) = parse_profile("clash", profile).unwrap();
+        assert!(skipped.is_empty());
+        let written: Vec<String> = servers.iter().map(Server::to_string).collect();
+        assert_eq!(
+            written,
+            [
+                "{ name: 'hk', type: trojan, server: 'a.example', port: 443, password: 'pw', udp: true, \
+                 sni: s.example, network: ws, ws-opts: { path: '/ws' } }",
+                "{ name: 'jp', type: ss, server: 'b.example', port: 8388, cipher: aes-256-gcm, password: 'pw', \
+                 udp: true, plugin: obfs, plugin-opts: { mode: tls, host: q.example } }",
+                "{ name: 'wg', type: wireguard, server: 'c.example', port: 51820, private-key: 'priv', \
+                 public-key: 'pub', udp: true, reserved: [1,2,3] }",
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_proxies_are_skipped() {
+        let profile = "\
+proxies:
+  - { name: hk, type: trojan, server: a.example, port: 443, password: pw }
+  - { name: h1, type: hysteria, server: b.example, port: 443, auth-str: x }
+  - { name: nopw, type: trojan, server: d.example, port: 443 }
+  - { name: badport, type: ss, server: e.example, port: 99999, cipher: x, password: y }
+";
+        let (servers, skipped) = parse_profile("clash", profile).unwrap();
+        assert_eq!(servers.len(), 1);
+        let skipped: Vec<_> = skipped
+            .iter()
+            .map(|s| (s.position.as_str(), s.protocol.as_str(), s.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("proxy 2", "hysteria", "h1"),
+                ("proxy 3", "trojan", "nopw"),
+                ("proxy 4", "ss", "badport"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unquoted_numbers() {
+        let profile = "\
+proxies:
+  - { name: 1, type: trojan, server: a.example, port: 443, password: 12345678 }
+  - { name: v, type: vless, server: b.example, port: 443, uuid: u, tls: true, reality-opts: { public-key: KEY, short-id: 1234 } }
+  - { name: ss, type: ss, server: c.example, port: '8388', cipher: aes-256-gcm, password: 0.5 }
+";
+        let (servers, skipped) = parse_profile("clash", profile).unwrap();
+        assert!(skipped.is_empty());
+        let written: Vec<String> = servers.iter().map(Server::to_string).collect();
+        assert_eq!(
+            written,
+            [
+                "{ name: '1', type: trojan, server: 'a.example', port: 443, password: '12345678', udp: true }",
+                "{ name: 'v', type: vless, server: 'b.example', port: 443, uuid: u, udp: true, tls: true, \
+                 reality-opts: { public-key: KEY, short-id: '1234' } }",
+                "{ name: 'ss', type: ss, server: 'c.example', port: 8388, cipher: aes-256-gcm, password: '0.5', \
+                 udp: true }",
+            ]
+        );
+    }
+
+    #[test]
+    fn not_a_profile() {
+        assert!(parse_profile("clash", "proxies: 3").is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use crate::Format;
+
+pub const USAGE: &str = "\
+usage: clash_profile [-c CONFIG] [--strict] [COMMAND] [OPTIONS]
+
+commands:
+  generate      fetch the subscriptions and write the profile (the default)
+    -o, --output PATH     where to write the profile
+    -s, --source ENTRY    a subscription to use instead of the configured ones,
+                          as `name=uri`; may be repeated
+  list          print the servers of the subscriptions as a table
+    -s, --source ENTRY
+  check         check that the rules and proxy groups only refer to groups
+                and proxies that exist
+    -s, --source ENTRY
+    -r, --rules PATH      the rules to check instead of the configured ones
+  convert [INPUT]
+                convert a subscription, read from a URL, a file or `-` for
+                standard input (the default), to another format
+    -f, --from FORMAT     links, clash, sip008 or sing-box; detected if omitted
+    -t, --to FORMAT       clash (the default) or sing-box
+    -o, --output PATH     where to write the result, standard output by default
+
+options:
+  -c, --config PATH       the config file, `clash_profile.yaml` by default
+      --strict            fail on share links that cannot be parsed instead of
+                          skipping them
+  -h, --help              print this help
+";
+
+/// Parsed command-line arguments.
+#[derive(Debug)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub strict: bool,
+    pub command: Command,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Generate {
+        output: Option<PathBuf>,
+        sources: Vec<String>,
+    },
+    List {
+        sources: Vec<String>,
+    },
+    Check {
+        sources: Vec<String>,
+        rules: Option<PathBuf>,
+    },
+    Convert {
+        input: String,
+        from: Option<Format>,
+        to: Format,
+        output: Option<PathBuf>,
+    },
+    Help,
+}
+
+impl Cli {
+    /// Parses the arguments, without the program name. Options may come
+    /// before or after the command.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+        let mut args = args.into_iter();
+        let mut config = None;
+        let mut strict = false;
+        let mut command: Option<String> = None;
+        let mut output = None;
+        let mut sources = vec![];
+        let mut rules = None;
+        let mut from = None;
+        let mut to = None;
+        let mut input = None;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    return Ok(Cli {
+                        config,
+                        strict,
+                        command: Command::Help,
+                    })
+                }
+                "-c" | "--config" => config = Some(PathBuf::from(value()?)),
+                "--strict" => strict = true,
+                "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+                "-s" | "--source" => sources.push(value()?),
+                "-r" | "--rules" => rules = Some(PathBuf::from(value()?)),
+                "-f" | "--from" => from = Some(value()?.parse()?),
+                "-t" | "--to" => to = Some(value()?.parse()?),
+                "generate" | "list" | "check" | "convert" if command.is_none() => {
+                    command = Some(arg)
+                }
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("unknown option {}", arg))
+                }
+                _ if command.as_deref() == Some("convert") && input.is_none() => input = Some(arg),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+        let command = command.unwrap_or_else(|| String::from("generate"));
+        let only = |option: &str, given: bool, commands: &str| {
+            if given && !commands.split('/').any(|c| c == command) {
+                Err(format!("{} only applies to {}", option, commands))
+            } else {
+                Ok(())
+            }
+        };
+        only("--output", output.is_some(), "generate/convert")?;
+        only("--source", !sources.is_empty(), "generate/list/check")?;
+        only("--rules", rules.is_some(), "check")?;
+        only("--from", from.is_some(), "convert")?;
+        only("--to", to.is_some(), "convert")?;
+        let command = match command.as_str() {
+            "generate" => Command::Generate { output, sources },
+            "list" => Command::List { sources },
+            "check" => Command::Check { sources, rules },
+            _ => {
+                let to = to.unwrap_or(Format::Clash);
+                if !matches!(to, Format::Clash | Format::SingBox) {
+                    return Err(String::from("convert can only write clash or sing-box"));
+                }
+                Command::Convert {
+                    input: input.unwrap_or_else(|| String::from("-")),
+                    from,
+                    to,
+                    output,
+                }
+            }
+        };
+        Ok(Cli {
+            config,
+            strict,
+            command,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, String> {
+        Cli::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn generate_by_default() {
+        let cli = parse("-c other.yaml --strict -s a=https://a.example -s b=sub.txt").unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("other.yaml")));
+        assert!(cli.strict);
+        match cli.command {
+            Command::Generate { output, sources } => {
+                assert_eq!(output, None);
+                assert_eq!(sources, ["a=https://a.example", "b=sub.txt"]);
+            }
+            command => panic!("expected generate, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn options_after_the_command() {
+        let cli = parse("check -r rules.yaml -c other.yaml").unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("other.yaml")));
+        match cli.command {
+            Command::Check { rules, .. } => assert_eq!(rules, Some(PathBuf::from("rules.yaml"))),
+            command => panic!("expected check, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn convert() {
+        let cli = parse("convert sub.txt -f sip008 -t sing-box -o out.json").unwrap();
+        match cli.command {
+            Command::Convert {
+                input,
+                from,
+                to,
+                output,
+            } => {
+                assert_eq!(input, "sub.txt");
+                assert_eq!(from, Some(Format::Sip008));
+                assert_eq!(to, Format::SingBox);
+                assert_eq!(output, Some(PathBuf::from("out.json")));
+            }
+            command => panic!("expected convert, got {:?}", command),
+        }
+        let cli = parse("convert").unwrap();
+        assert!(
+            matches!(cli.command, Command::Convert { input, from: None, to: Format::Clash, .. } if input == "-")
+        );
+    }
+
+    #[test]
+    fn help() {
+        assert!(matches!(
+            parse("list --help").unwrap().command,
+            Command::Help
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("--bogus").unwrap_err(), "unknown option --bogus");
+        assert_eq!(
+            parse("list extra").unwrap_err(),
+            "unexpected argument extra"
+        );
+        assert_eq!(
+            parse("list -o out.yaml").unwrap_err(),
+            "--output only applies to generate/convert"
+        );
+        assert_eq!(parse("-s").unwrap_err(), "-s needs a value");
+        assert!(parse("convert -t sip008").is_err());
+    }
+}
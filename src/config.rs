@@ -0,0 +1,234 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::fetch::FetchOptions;
+use crate::filter::Filters;
+use crate::group::GroupOptions;
+use crate::region::{self, Region};
+
+/// Where the config file is looked for when `$CONFIG_PATH` is not set.
+const DEFAULT_PATH: &str = "clash_profile.yaml";
+
+/// Runtime settings, read from a YAML file and then overridden by
+/// environment variables. Every setting is optional.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the generated profile is written.
+    pub profile_path: PathBuf,
+    /// The head of the profile, the embedded `clash_static_config.yaml` if
+    /// unset.
+    pub static_config: Option<PathBuf>,
+    /// The rules appended to the profile, the embedded `rules` if unset.
+    pub rules: Option<PathBuf>,
+    /// `name=uri` entries, see [`crate::subscription::Subscription`].
+    pub subscriptions: Vec<String>,
+    /// `comment`, `group` or empty.
+    pub userinfo_output: String,
+    pub cache_dir: Option<PathBuf>,
+    pub wireguard_confs: Vec<PathBuf>,
+    pub fetch: FetchOptions,
+    pub filters: Filters,
+    /// The regions proxies are grouped by, the built-in table if unset.
+    pub regions: Vec<Region>,
+    /// Region groups listed first, in this order; the others follow
+    /// alphabetically.
+    pub group_order: Vec<String>,
+    pub proxy_groups: GroupOptions,
+    /// Fail on share links that cannot be parsed instead of skipping them.
+    pub strict: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            profile_path: PathBuf::from("profile.yaml"),
+            static_config: None,
+            rules: None,
+            subscriptions: vec![],
+            userinfo_output: String::new(),
+            cache_dir: None,
+            wireguard_confs: vec![],
+            fetch: FetchOptions::default(),
+            filters: Filters::default(),
+            regions: region::builtin(),
+            group_order: ["HongKong", "Taiwan", "Japan", "Singapore", "US"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            proxy_groups: GroupOptions::default(),
+            strict: false,
+        }
+    }
+}
+
+impl Config {
+    /// Reads `path`, `$CONFIG_PATH`, or `clash_profile.yaml` if there is
+    /// one, and applies the environment overrides.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = path
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("CONFIG_PATH").map(PathBuf::from));
+        let mut config = match path {
+            Some(path) => Config::read(&path)?,
+            None if Path::new(DEFAULT_PATH).exists() => Config::read(Path::new(DEFAULT_PATH))?,
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path).map_err(Error::io(path))?;
+        serde_yaml::from_str(&text)
+            .map_err(|e| Error::Config(format!("invalid {}: {}", path.display(), e)))
+    }
+
+    /// `$SUBSCRIPTIONS` replaces the configured subscriptions, and
+    /// `$PROFILE_URI` is added after them. `$INCLUDE_NAME`, `$EXCLUDE_HOST`
+    /// and so on set `filters.include.name`, `filters.exclude.host`.
+    /// `$REGIONS` holds `group=keyword,keyword` entries separated by `;`, and
+    /// `$GROUP_ORDER` group names separated by `,`. `$GROUP_TYPES` holds
+    /// `group=type` entries separated by `,`.
+    /// Other variables replace the setting of the same name.
+    fn apply_env(&mut self) -> Result<()> {
+        if let Some(path) = var("PROFILE_PATH")? {
+            self.profile_path = path;
+        }
+        if let Some(path) = var("STATIC_CONFIG")? {
+            self.static_config = Some(path);
+        }
+        if let Some(path) = var("RULES")? {
+            self.rules = Some(path);
+        }
+        if let Some(entries) = var::<String>("SUBSCRIPTIONS")? {
+            self.subscriptions = entries.split_whitespace().map(String::from).collect();
+        }
+        self.subscriptions.extend(var("PROFILE_URI")?);
+        if let Some(output) = var("USERINFO_OUTPUT")? {
+            self.userinfo_output = output;
+        }
+        if let Some(dir) = var("CACHE_DIR")? {
+            self.cache_dir = Some(dir);
+        }
+        if let Some(paths) = env::var_os("WIREGUARD_CONFS") {
+            self.wireguard_confs = env::split_paths(&paths).collect();
+        }
+        if let Some(strict) = var("STRICT")? {
+            self.strict = strict;
+        }
+        if let Some(regions) = var::<String>("REGIONS")? {
+            self.regions = regions
+                .split(';')
+                .filter(|r| !r.trim().is_empty())
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|e| Error::Config(format!("invalid $REGIONS: {}", e)))?;
+        }
+        if let Some(order) = var::<String>("GROUP_ORDER")? {
+            self.group_order = order
+                .split(',')
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        let groups = &mut self.proxy_groups;
+        if let Some(kind) = var("GROUP_TYPE")? {
+            groups.region_type = kind;
+        }
+        if let Some(types) = var::<String>("GROUP_TYPES")? {
+            groups.types = types
+                .split(',')
+                .filter(|t| !t.trim().is_empty())
+                .map(|t| {
+                    let (name, kind) = t
+                        .split_once('=')
+                        .ok_or_else(|| format!("expected group=type, got {}", t))?;
+                    Ok((String::from(name.trim()), kind.trim().parse()?))
+                })
+                .collect::<std::result::Result<_, String>>()
+                .map_err(|e| Error::Config(format!("invalid $GROUP_TYPES: {}", e)))?;
+        }
+        if let Some(auto) = var("GROUP_AUTO")? {
+            groups.auto = auto;
+        }
+        if let Some(url) = var("GROUP_TEST_URL")? {
+            groups.test_url = url;
+        }
+        if let Some(secs) = var("GROUP_TEST_INTERVAL")? {
+            groups.interval = secs;
+        }
+        if let Some(ms) = var("GROUP_TEST_TOLERANCE")? {
+            groups.tolerance = ms;
+        }
+        if let Some(lazy) = var("GROUP_TEST_LAZY")? {
+            groups.lazy = lazy;
+        }
+        let filters = &mut self.filters;
+        for (prefix, patterns) in [
+            ("INCLUDE", &mut filters.include),
+            ("EXCLUDE", &mut filters.exclude),
+        ] {
+            for (field, pattern) in patterns.fields_mut() {
+                if let Some(re) = var(&format!("{}_{}", prefix, field.to_uppercase()))? {
+                    *pattern = Some(re);
+                }
+            }
+        }
+        let fetch = &mut self.fetch;
+        if let Some(secs) = var("FETCH_CONNECT_TIMEOUT")? {
+            fetch.connect_timeout = secs;
+        }
+        if let Some(secs) = var("FETCH_TIMEOUT")? {
+            fetch.timeout = secs;
+        }
+        if let Some(retries) = var("FETCH_RETRIES")? {
+            fetch.retries = retries;
+        }
+        if let Some(user_agent) = var("FETCH_USER_AGENT")? {
+            fetch.user_agent = user_agent;
+        }
+        if let Some(proxy) = var("FETCH_PROXY")? {
+            fetch.proxy = Some(proxy);
+        }
+        Ok(())
+    }
+
+    pub fn static_config(&self) -> Result<Vec<u8>> {
+        read_or(
+            self.static_config.as_deref(),
+            include_bytes!("../clash_static_config.yaml"),
+        )
+    }
+
+    pub fn rules(&self) -> Result<Vec<u8>> {
+        read_or(self.rules.as_deref(), include_bytes!("../rules"))
+    }
+}
+
+/// Reads an environment variable, treating an empty one as unset.
+fn var<T: FromStr>(key: &str) -> Result<Option<T>>
+where
+    T::Err: ToString,
+{
+    match env::var(key) {
+        Ok(v) if !v.is_empty() => v
+            .parse()
+            .map(Some)
+            .map_err(|e: T::Err| Error::Config(format!("invalid ${}: {}", key, e.to_string()))),
+        _ => Ok(None),
+    }
+}
+
+fn read_or(path: Option<&Path>, embedded: &[u8]) -> Result<Vec<u8>> {
+    match path {
+        Some(path) => fs::read(path).map_err(Error::io(path)),
+        None => Ok(embedded.to_vec()),
+    }
+}
@@ -0,0 +1,96 @@
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+
+const LENIENT: GeneralPurposeConfig = GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+    .with_decode_allow_trailing_bits(true);
+const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
+/// Decodes base64 in either the standard or the URL-safe alphabet, with or
+/// without padding. Whitespace, such as the line breaks some providers wrap
+/// their payload with, is ignored.
+pub fn decode_base64(s: &str) -> Result<Vec<u8>, String> {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let engine = if s.contains(['-', '_']) {
+        &URL_SAFE
+    } else {
+        &STANDARD
+    };
+    engine
+        .decode(s.trim_end_matches('='))
+        .map_err(|e| format!("invalid base64: {}", e))
+}
+
+/// Like [`decode_base64`], for payloads that must be UTF-8 text.
+pub fn decode_base64_str(s: &str) -> Result<String, String> {
+    let bytes = decode_base64(s)?;
+    String::from_utf8(bytes).map_err(|e| format!("invalid utf-8: {}", e))
+}
+
+/// Turns a subscription body into its share links, one per line, along with
+/// their line numbers.
+///
+/// The body may be base64 in any of the flavours [`decode_base64`] accepts,
+/// or an already decoded plain list of links. Byte order marks, CRLF line
+/// endings, surrounding whitespace and blank lines are dropped. Line numbers
+/// count from 1 and refer to the decoded text.
+pub fn decode_subscription(body: &[u8]) -> Result<Vec<(usize, String)>, String> {
+    let body = String::from_utf8_lossy(body);
+    let body = strip_bom(&body);
+    let text = if body.contains("://") {
+        String::from(body)
+    } else {
+        decode_base64_str(body.trim())?
+    };
+    Ok(strip_bom(&text)
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(n, l)| (n + 1, String::from(l)))
+        .collect())
+}
+
+fn strip_bom(s: &str) -> &str {
+    s.strip_prefix('\u{feff}').unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_flavours() {
+        for s in ["aGk/Pz4=", "aGk/Pz4", "aGk_Pz4", "aGk/\nPz4=\n"] {
+            assert_eq!(decode_base64_str(s).unwrap(), "hi??>");
+        }
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn base64_subscription() {
+        let body = "\u{feff}dHJvamFuOi8vYUBiOjEKCnNzOi8vYUBiOjINCg";
+        assert_eq!(
+            decode_subscription(body.as_bytes()).unwrap(),
+            [
+                (1, String::from("trojan://a@b:1")),
+                (3, String::from("ss://a@b:2"))
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_subscription() {
+        let body = "\u{feff}trojan://a@b:1\r\n  \r\n ss://a@b:2 \r\n";
+        assert_eq!(
+            decode_subscription(body.as_bytes()).unwrap(),
+            [
+                (1, String::from("trojan://a@b:1")),
+                (3, String::from("ss://a@b:2"))
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can stop a run. Each kind exits with its own code, so
+/// that scripts can tell a provider being down from a bad link.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line, the config file or an environment variable is
+    /// wrong.
+    Config(String),
+    /// A subscription could not be downloaded and there is no cached copy.
+    Fetch { source: String, reason: String },
+    /// A subscription body is not valid base64, JSON or YAML, or not shaped
+    /// like the format it is read as, such as a Clash profile whose
+    /// `proxies` is not a list.
+    Decode { source: String, reason: String },
+    /// A server of a subscription or a WireGuard config could not be read.
+    Parse { source: String, reason: String },
+    /// Reading or writing a local file failed.
+    Io { path: PathBuf, error: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 2,
+            Error::Fetch { .. } => 3,
+            Error::Decode { .. } => 4,
+            Error::Parse { .. } => 5,
+            Error::Io { .. } => 6,
+        }
+    }
+
+    /// Wraps an IO error with the file it happened on.
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |error| Error::Io { path, error }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(reason) => write!(f, "{}", reason),
+            Error::Fetch { source, reason } => write!(f, "{}: cannot fetch: {}", source, reason),
+            Error::Decode { source, reason } => write!(f, "{}: cannot decode: {}", source, reason),
+            Error::Parse { source, reason } => write!(f, "{}: {}", source, reason),
+            Error::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
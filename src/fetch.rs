@@ -0,0 +1,397 @@
+use std::future::Future;
+use std::io::{self, Read};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use base64::Engine;
+use hyper::body::Bytes;
+use hyper::header::{
+    HeaderMap, HeaderName, ACCEPT_ENCODING, CONTENT_ENCODING, PROXY_AUTHORIZATION, USER_AGENT,
+};
+use hyper::service::Service;
+use hyper::{Body, Client, Request, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+use serde::{de, Deserialize, Deserializer};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// How subscriptions are downloaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchOptions {
+    /// Seconds to wait for a connection.
+    pub connect_timeout: u64,
+    /// Seconds a whole request may take, from sending it to reading the last
+    /// byte.
+    pub timeout: u64,
+    /// How many times a failed request is retried, waiting 1s, 2s, 4s...
+    /// in between, up to a minute.
+    pub retries: u32,
+    pub user_agent: String,
+    /// An `http://` or `socks5://` proxy to download through.
+    #[serde(deserialize_with = "deserialize_proxy")]
+    pub proxy: Option<Uri>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            connect_timeout: 10,
+            timeout: 30,
+            retries: 3,
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            proxy: None,
+        }
+    }
+}
+
+fn deserialize_proxy<'de, D>(deserializer: D) -> Result<Option<Uri>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(proxy) => proxy.parse().map(Some).map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+/// A response with its body read and decompressed.
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// An HTTP(S) client that applies [`FetchOptions`].
+#[derive(Clone)]
+pub struct Fetcher {
+    client: Client<HttpsConnector<ProxyConnector>>,
+    options: FetchOptions,
+}
+
+impl Fetcher {
+    pub fn new(options: FetchOptions) -> Fetcher {
+        let connector = ProxyConnector {
+            proxy: options.proxy.clone(),
+            connect_timeout: Duration::from_secs(options.connect_timeout),
+        };
+        let https = HttpsConnector::new_with_connector(connector);
+        let client = Client::builder().build::<_, Body>(https);
+        Fetcher { client, options }
+    }
+
+    /// Sends a GET request with the extra `headers`, retrying on network
+    /// errors, timeouts and 5xx or 429 responses. Errors that another try
+    /// would only repeat, such as an unsupported scheme, fail at once.
+    pub async fn get(
+        &self,
+        uri: &Uri,
+        headers: &[(HeaderName, String)],
+    ) -> Result<Response, String> {
+        let mut attempt = 0;
+        loop {
+            let result = self.try_get(uri, headers).await;
+            let error = match result {
+                Ok(resp)
+                    if resp.status.is_server_error()
+                        || resp.status == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    if attempt >= self.options.retries {
+                        return Ok(resp);
+                    }
+                    format!("HTTP {}", resp.status)
+                }
+                Ok(resp) => return Ok(resp),
+                Err(Failure { reason, retry }) if !retry || attempt >= self.options.retries => {
+                    return Err(reason)
+                }
+                Err(Failure { reason, .. }) => reason,
+            };
+            let delay = retry_delay(attempt);
+            eprintln!(
+                "warning: {}: {}, retrying in {}s",
+                uri.host().unwrap_or_default(),
+                error,
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn try_get(
+        &self,
+        uri: &Uri,
+        headers: &[(HeaderName, String)],
+    ) -> Result<Response, Failure> {
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(Failure::fatal(format!("unsupported URL {}", uri)));
+        }
+        let mut req = Request::get(uri.clone())
+            .header(USER_AGENT, &self.options.user_agent)
+            .header(ACCEPT_ENCODING, "gzip, deflate");
+        for (k, v) in headers {
+            req = req.header(k, v);
+        }
+        let req = req
+            .body(Body::empty())
+            .map_err(|e| Failure::fatal(e.to_string()))?;
+        let resp = async {
+            let resp = self.client.request(req).await.map_err(|e| Failure {
+                reason: e.to_string(),
+                retry: !e.is_user(),
+            })?;
+            let (parts, body) = resp.into_parts();
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|e| Failure::transient(e.to_string()))?;
+            Ok::<_, Failure>((parts, body))
+        };
+        let (parts, body) = tokio::time::timeout(Duration::from_secs(self.options.timeout), resp)
+            .await
+            .map_err(|_| Failure::transient(String::from("timed out")))??;
+        let body = match parts.headers.get(CONTENT_ENCODING).map(|v| v.as_bytes()) {
+            Some(b"gzip") => inflate(flate2::read::GzDecoder::new(&body[..])),
+            Some(b"deflate") => inflate(flate2::read::ZlibDecoder::new(&body[..])),
+            _ => Ok(body),
+        }
+        .map_err(Failure::fatal)?;
+        Ok(Response {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        })
+    }
+}
+
+/// Why a request failed, and whether trying again may help.
+struct Failure {
+    reason: String,
+    retry: bool,
+}
+
+impl Failure {
+    fn transient(reason: String) -> Failure {
+        Failure {
+            reason,
+            retry: true,
+        }
+    }
+
+    fn fatal(reason: String) -> Failure {
+        Failure {
+            reason,
+            retry: false,
+        }
+    }
+}
+
+/// How long to wait before retrying the `attempt`th attempt, counting from
+/// 0: twice as long each time, but never more than a minute.
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt).min(60))
+}
+
+fn inflate(mut decoder: impl Read) -> Result<Bytes, String> {
+    let mut body = vec![];
+    decoder
+        .read_to_end(&mut body)
+        .map_err(|e| format!("invalid compressed body: {}", e))?;
+    Ok(body.into())
+}
+
+/// Opens TCP connections for hyper, either directly or tunneled through an
+/// HTTP CONNECT or SOCKS5 proxy.
+#[derive(Debug, Clone)]
+pub struct ProxyConnector {
+    proxy: Option<Uri>,
+    connect_timeout: Duration,
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connector = self.clone();
+        Box::pin(async move {
+            tokio::time::timeout(connector.connect_timeout, connector.connect(dst))
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))?
+        })
+    }
+}
+
+impl ProxyConnector {
+    async fn connect(&self, dst: Uri) -> io::Result<TcpStream> {
+        let (host, port) = host_port(&dst)?;
+        let proxy = match &self.proxy {
+            None => return TcpStream::connect((host.as_str(), port)).await,
+            Some(proxy) => proxy,
+        };
+        let (proxy_host, proxy_port) = host_port(proxy)?;
+        let mut stream = TcpStream::connect((proxy_host.as_str(), proxy_port)).await?;
+        // user:password in the proxy URI, if any
+        let credentials = proxy
+            .authority()
+            .and_then(|a| a.as_str().rsplit_once('@'))
+            .map(|(userinfo, _)| {
+                let (user, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+                let decode = crate::percent_decode;
+                (decode(user), decode(password))
+            });
+        match proxy.scheme_str() {
+            Some("socks5" | "socks5h") => {
+                socks5_handshake(&mut stream, &host, port, credentials).await?
+            }
+            Some("http") => http_connect(&mut stream, &host, port, credentials).await?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported proxy {}", proxy),
+                ))
+            }
+        }
+        Ok(stream)
+    }
+}
+
+fn host_port(uri: &Uri) -> io::Result<(String, u16)> {
+    let host = uri.host().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("no host in {}", uri))
+    })?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("https") => 443,
+        Some("socks5" | "socks5h") => 1080,
+        _ => 80,
+    });
+    Ok((String::from(host), port))
+}
+
+fn proxy_error(msg: &str) -> io::Error {
+    io::Error::other(format!("proxy: {}", msg))
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> io::Result<()> {
+    let target = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut req = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", target);
+    if let Some((user, password)) = credentials {
+        let token =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+        req.push_str(&format!("{}: Basic {}\r\n", PROXY_AUTHORIZATION, token));
+    }
+    req.push_str("\r\n");
+    stream.write_all(req.as_bytes()).await?;
+    // read the response head byte by byte so nothing of the tunnel is consumed
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(proxy_error("response head too long"));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    let status = String::from_utf8_lossy(&head);
+    let status = status.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(proxy_error(status)),
+    }
+}
+
+async fn socks5_handshake(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> io::Result<()> {
+    let greeting: &[u8] = match credentials {
+        Some(_) => &[5, 2, 0, 2],
+        None => &[5, 1, 0],
+    };
+    stream.write_all(greeting).await?;
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply).await?;
+    match (reply, credentials) {
+        ([5, 0], _) => {}
+        ([5, 2], Some((user, password))) => {
+            let mut auth = vec![1, socks5_len(&user, "username")?];
+            auth.extend(user.as_bytes());
+            auth.push(socks5_len(&password, "password")?);
+            auth.extend(password.as_bytes());
+            stream.write_all(&auth).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(proxy_error("socks5 authentication failed"));
+            }
+        }
+        _ => return Err(proxy_error("no acceptable socks5 authentication method")),
+    }
+    let mut req = vec![5, 1, 0, 3, socks5_len(host, "host name")?];
+    req.extend(host.as_bytes());
+    req.extend(port.to_be_bytes());
+    stream.write_all(&req).await?;
+    let mut head = [0; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0 {
+        return Err(proxy_error(&format!(
+            "socks5 connect failed with {}",
+            head[1]
+        )));
+    }
+    // skip the bound address and port
+    let len = match head[3] {
+        1 => 4,
+        4 => 16,
+        3 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_error("bad socks5 address type")),
+    };
+    let mut bound = vec![0; len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+/// SOCKS5 prefixes names and credentials with a one-byte length.
+fn socks5_len(value: &str, what: &str) -> io::Result<u8> {
+    u8::try_from(value.len())
+        .map_err(|_| proxy_error(&format!("socks5 {} longer than 255 bytes", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delays_double_up_to_a_minute() {
+        let delays: Vec<u64> = [0, 1, 2, 5, 6, 63, 64, u32::MAX]
+            .into_iter()
+            .map(|attempt| retry_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 32, 60, 60, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn unsupported_scheme_is_not_retried() {
+        let fetcher = Fetcher::new(FetchOptions::default());
+        let uri: Uri = "ftp://sub.example.com/x".parse().unwrap();
+        let started = std::time::Instant::now();
+        assert!(fetcher.get(&uri, &[]).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,152 @@
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::Server;
+
+/// The names providers give the pseudo-servers that only carry a notice,
+/// such as the traffic left, the expiry date or the provider's website.
+const DEFAULT_EXCLUDE: &str = "剩余流量|到期|官网";
+
+/// Decides which servers of the subscriptions make it into the profile. A
+/// server is kept if it matches every `include` pattern and no `exclude`
+/// pattern.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filters {
+    pub include: Patterns,
+    /// Excludes the provider's notices by name unless replaced.
+    pub exclude: Patterns,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Filters {
+            include: Patterns::default(),
+            exclude: Patterns {
+                name: Some(Regex::new(DEFAULT_EXCLUDE).unwrap()),
+                ..Patterns::default()
+            },
+        }
+    }
+}
+
+/// Regexes searched for in the fields of a server; unset ones are ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Patterns {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub name: Option<Regex>,
+    /// Matched against the Clash type, such as `ss` or `vmess`.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub protocol: Option<Regex>,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub host: Option<Regex>,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub port: Option<Regex>,
+}
+
+impl Patterns {
+    pub fn fields_mut(&mut self) -> [(&'static str, &mut Option<Regex>); 4] {
+        [
+            ("name", &mut self.name),
+            ("protocol", &mut self.protocol),
+            ("host", &mut self.host),
+            ("port", &mut self.port),
+        ]
+    }
+
+    fn fields(&self) -> [(&'static str, Option<&Regex>); 4] {
+        [
+            ("name", self.name.as_ref()),
+            ("protocol", self.protocol.as_ref()),
+            ("host", self.host.as_ref()),
+            ("port", self.port.as_ref()),
+        ]
+    }
+}
+
+impl Filters {
+    /// Why `server` is filtered out, or `None` if it is kept.
+    pub fn reject(&self, server: &Server) -> Option<String> {
+        let values = [
+            server.name(),
+            String::from(server.kind()),
+            String::from(server.host()),
+            server.port().to_string(),
+        ];
+        let include = self.include.fields().into_iter().zip(&values);
+        for ((field, pattern), value) in include {
+            match pattern {
+                Some(re) if !re.is_match(value) => {
+                    return Some(format!("{} does not match {}", field, re))
+                }
+                _ => {}
+            }
+        }
+        let exclude = self.exclude.fields().into_iter().zip(&values);
+        for ((field, pattern), value) in exclude {
+            match pattern {
+                Some(re) if re.is_match(value) => {
+                    return Some(format!("{} matches excluded {}", field, re))
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(pattern) => Regex::new(&pattern).map(Some).map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(link: &str) -> Server {
+        link.parse().unwrap()
+    }
+
+    #[test]
+    fn default_excludes_notices() {
+        let filters = Filters::default();
+        assert!(filters
+            .reject(&server("trojan://pw@t.example.com:443#HK 01"))
+            .is_none());
+        assert_eq!(
+            filters.reject(&server("trojan://pw@t.example.com:443#剩余流量：10 GB")),
+            Some(format!("name matches excluded {}", DEFAULT_EXCLUDE))
+        );
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let filters: Filters = serde_yaml::from_str(
+            "include: { protocol: '^(trojan|ss)$' }\nexclude: { port: '^80$' }",
+        )
+        .unwrap();
+        assert!(filters
+            .reject(&server("trojan://pw@t.example.com:443#剩余流量"))
+            .is_none());
+        assert_eq!(
+            filters.reject(&server("http://h.example.com:8080")),
+            Some(String::from("protocol does not match ^(trojan|ss)$"))
+        );
+        assert_eq!(
+            filters.reject(&server("trojan://pw@t.example.com:80")),
+            Some(String::from("port matches excluded ^80$"))
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(serde_yaml::from_str::<Filters>("include: { name: '(' }").is_err());
+    }
+}
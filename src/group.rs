@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// How a proxy group picks its proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GroupType {
+    /// By hand, on the dashboard.
+    Select,
+    /// The one with the lowest latency.
+    UrlTest,
+    /// The first one that is up.
+    Fallback,
+    /// Spread connections over all of them.
+    LoadBalance,
+}
+
+impl GroupType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupType::Select => "select",
+            GroupType::UrlTest => "url-test",
+            GroupType::Fallback => "fallback",
+            GroupType::LoadBalance => "load-balance",
+        }
+    }
+}
+
+impl FromStr for GroupType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "select" => Ok(GroupType::Select),
+            "url-test" => Ok(GroupType::UrlTest),
+            "fallback" => Ok(GroupType::Fallback),
+            "load-balance" => Ok(GroupType::LoadBalance),
+            _ => Err(format!(
+                "unknown group type {}, expected select, url-test, fallback or load-balance",
+                s
+            )),
+        }
+    }
+}
+
+/// How the region groups and the groups built on them are generated.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupOptions {
+    /// The type of the region groups.
+    pub region_type: GroupType,
+    /// Types of single groups by name, such as `Choice` or `US`, overriding
+    /// `region_type`.
+    pub types: HashMap<String, GroupType>,
+    /// Add a `url-test` group named `<region> Auto` after each region group
+    /// of type `select`.
+    pub auto: bool,
+    /// What `url-test`, `fallback` and `load-balance` groups fetch to check
+    /// their proxies.
+    pub test_url: String,
+    /// Seconds between checks.
+    pub interval: u32,
+    /// Milliseconds by which a `url-test` group's best proxy must beat the
+    /// current one to replace it.
+    pub tolerance: u32,
+    /// Only check proxies while the group is in use.
+    pub lazy: bool,
+}
+
+impl Default for GroupOptions {
+    fn default() -> Self {
+        GroupOptions {
+            region_type: GroupType::Select,
+            types: HashMap::new(),
+            auto: false,
+            test_url: String::from("http://www.gstatic.com/generate_204"),
+            interval: 300,
+            tolerance: 50,
+            lazy: true,
+        }
+    }
+}
+
+impl GroupOptions {
+    /// The type of the group `name`, `default` unless overridden.
+    pub fn type_of(&self, name: &str, default: GroupType) -> GroupType {
+        self.types.get(name).copied().unwrap_or(default)
+    }
+}
@@ -0,0 +1,174 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// A region proxies are grouped by, recognized by keywords in their names.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    /// The name of the proxy group its proxies are put in.
+    pub group: String,
+    /// Names, codes and flags that mark a proxy as being in the region,
+    /// matched case-sensitively. A keyword starting or ending with an ASCII
+    /// letter only matches where it is not next to another one, so that `US`
+    /// matches `US-01` and `🇺🇸US` but not `RUSSIA`.
+    pub keywords: Vec<String>,
+}
+
+/// Parses the `group=keyword,keyword` form used by `$REGIONS`.
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (group, keywords) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected group=keywords, got {}", s))?;
+        Ok(Region {
+            group: String::from(group.trim()),
+            keywords: keywords
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+}
+
+impl Region {
+    pub fn matches(&self, name: &str) -> bool {
+        self.keywords.iter().any(|k| contains_keyword(name, k))
+    }
+}
+
+/// The group of the first region `name` matches, if any.
+pub fn find<'a>(regions: &'a [Region], name: &str) -> Option<&'a str> {
+    regions
+        .iter()
+        .find(|r| r.matches(name))
+        .map(|r| r.group.as_str())
+}
+
+fn contains_keyword(name: &str, keyword: &str) -> bool {
+    let letter = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphabetic());
+    let check_before = letter(keyword.chars().next());
+    let check_after = letter(keyword.chars().next_back());
+    !keyword.is_empty()
+        && name.match_indices(keyword).any(|(i, _)| {
+            let touches_before = check_before && letter(name[..i].chars().next_back());
+            let touches_after = check_after && letter(name[i + keyword.len()..].chars().next());
+            !touches_before && !touches_after
+        })
+}
+
+/// Group name, ISO 3166 code and other keywords of the built-in regions. The
+/// code and its flag are keywords too. Regions are tried in order.
+#[rustfmt::skip]
+const BUILTIN: &[(&str, &str, &[&str])] = &[
+    ("HongKong", "HK", &["香港", "Hong Kong", "HongKong", "HKG"]),
+    ("Taiwan", "TW", &["台湾", "台灣", "台北", "Taiwan", "Taipei", "TPE"]),
+    ("Macau", "MO", &["澳门", "澳門", "Macau", "Macao", "MFM"]),
+    ("Japan", "JP", &["日本", "东京", "大阪", "Japan", "Tokyo", "Osaka", "NRT", "HND", "KIX"]),
+    ("Korea", "KR", &["韩国", "韓國", "首尔", "Korea", "Seoul", "ICN"]),
+    ("Singapore", "SG", &["新加坡", "狮城", "Singapore", "SIN"]),
+    ("US", "US", &["美国", "美國", "洛杉矶", "硅谷", "西雅图", "纽约", "United States", "USA",
+        "Los Angeles", "San Jose", "Seattle", "New York", "LAX", "SJC", "SEA", "JFK"]),
+    ("Canada", "CA", &["加拿大", "Canada", "Toronto", "Vancouver", "YYZ", "YVR"]),
+    ("Mexico", "MX", &["墨西哥", "Mexico", "MEX"]),
+    ("Brazil", "BR", &["巴西", "Brazil", "São Paulo", "Sao Paulo", "GRU"]),
+    ("Argentina", "AR", &["阿根廷", "Argentina", "EZE"]),
+    ("Chile", "CL", &["智利", "Chile", "SCL"]),
+    ("UK", "GB", &["英国", "英國", "伦敦", "United Kingdom", "Britain", "London", "UK", "LHR"]),
+    ("Germany", "DE", &["德国", "德國", "法兰克福", "Germany", "Frankfurt", "FRA"]),
+    ("France", "FR", &["法国", "法國", "巴黎", "France", "Paris", "CDG"]),
+    ("Netherlands", "NL", &["荷兰", "荷蘭", "阿姆斯特丹", "Netherlands", "Amsterdam", "AMS"]),
+    ("Switzerland", "CH", &["瑞士", "Switzerland", "Zurich", "ZRH"]),
+    ("Italy", "IT", &["意大利", "義大利", "Italy", "Milan", "MXP"]),
+    ("Spain", "ES", &["西班牙", "Spain", "Madrid", "MAD"]),
+    ("Ireland", "IE", &["爱尔兰", "愛爾蘭", "Ireland", "Dublin", "DUB"]),
+    ("Sweden", "SE", &["瑞典", "Sweden", "Stockholm", "ARN"]),
+    ("Finland", "FI", &["芬兰", "芬蘭", "Finland", "Helsinki", "HEL"]),
+    ("Norway", "NO", &["挪威", "Norway", "Oslo", "OSL"]),
+    ("Poland", "PL", &["波兰", "波蘭", "Poland", "Warsaw", "WAW"]),
+    ("Austria", "AT", &["奥地利", "奧地利", "Austria", "Vienna", "VIE"]),
+    ("Russia", "RU", &["俄罗斯", "俄羅斯", "莫斯科", "Russia", "Moscow", "SVO"]),
+    ("Ukraine", "UA", &["乌克兰", "烏克蘭", "Ukraine", "Kyiv", "KBP"]),
+    ("Turkey", "TR", &["土耳其", "Turkey", "Türkiye", "Istanbul", "IST"]),
+    ("Israel", "IL", &["以色列", "Israel", "TLV"]),
+    ("UAE", "AE", &["阿联酋", "迪拜", "UAE", "United Arab Emirates", "Dubai", "DXB"]),
+    ("Thailand", "TH", &["泰国", "泰國", "曼谷", "Thailand", "Bangkok", "BKK"]),
+    ("Vietnam", "VN", &["越南", "Vietnam", "Hanoi", "SGN", "HAN"]),
+    ("Malaysia", "MY", &["马来西亚", "馬來西亞", "Malaysia", "Kuala Lumpur", "KUL"]),
+    ("Philippines", "PH", &["菲律宾", "菲律賓", "Philippines", "Manila", "MNL"]),
+    // before India, whose Chinese name is part of Indonesia's
+    ("Indonesia", "ID", &["印尼", "印度尼西亚", "Indonesia", "Jakarta", "CGK"]),
+    ("India", "IN", &["印度", "India", "Mumbai", "BOM"]),
+    ("Australia", "AU", &["澳大利亚", "澳洲", "悉尼", "Australia", "Sydney", "SYD"]),
+    ("NewZealand", "NZ", &["新西兰", "紐西蘭", "New Zealand", "Auckland", "AKL"]),
+    ("SouthAfrica", "ZA", &["南非", "South Africa", "Johannesburg", "JNB"]),
+    ("Egypt", "EG", &["埃及", "Egypt", "Cairo", "CAI"]),
+    ("Kazakhstan", "KZ", &["哈萨克斯坦", "Kazakhstan", "ALA"]),
+];
+
+/// The regions used unless the config gives its own.
+pub fn builtin() -> Vec<Region> {
+    BUILTIN
+        .iter()
+        .map(|(group, code, keywords)| {
+            let mut all: Vec<String> = keywords.iter().map(|k| String::from(*k)).collect();
+            all.push(String::from(*code));
+            all.push(flag(code));
+            Region {
+                group: String::from(*group),
+                keywords: all,
+            }
+        })
+        .collect()
+}
+
+/// The flag emoji of an ISO 3166 code, made of two regional indicator
+/// symbols.
+fn flag(code: &str) -> String {
+    code.chars()
+        .filter_map(|c| char::from_u32(0x1F1E6 + (c as u32 - 'A' as u32)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_match_whole_words() {
+        let regions = builtin();
+        assert_eq!(find(&regions, "US-01"), Some("US"));
+        assert_eq!(find(&regions, "🇺🇸US"), Some("US"));
+        assert_eq!(find(&regions, "美国 03"), Some("US"));
+        assert_eq!(find(&regions, "RUSSIA-01"), None);
+        assert_eq!(find(&regions, "Russia 01"), Some("Russia"));
+        assert_eq!(find(&regions, "🇰🇷 Seoul 01"), Some("Korea"));
+        assert_eq!(find(&regions, "plain"), None);
+    }
+
+    #[test]
+    fn indonesia_before_india() {
+        let regions = builtin();
+        assert_eq!(find(&regions, "印度尼西亚"), Some("Indonesia"));
+        assert_eq!(find(&regions, "印度 01"), Some("India"));
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(flag("JP"), "🇯🇵");
+    }
+
+    #[test]
+    fn from_env() {
+        let region: Region = " Home = 家, Home ,".parse().unwrap();
+        assert_eq!(region.group, "Home");
+        assert_eq!(region.keywords, ["家", "Home"]);
+        assert!(region.matches("My Home 1"));
+        assert!(!region.matches("HomeLab"));
+        assert!("Home".parse::<Region>().is_err());
+    }
+}
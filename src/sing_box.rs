@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::skipped::Skipped;
+use crate::{
+    required, Http, Hysteria2, Network, Plugin, Reality, Reserved, Server, ShadowSocks, Socks5,
+    Trojan, Tuic, Vless, Vmess, WireGuard,
+};
+
+/// Either a whole sing-box config or just its `outbounds` array. Outbounds
+/// are read one by one, so that one we cannot read does not spoil the
+/// others.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Config {
+    Full { outbounds: Vec<Value> },
+    Outbounds(Vec<Value>),
+}
+
+/// One sing-box outbound. Fields that only some outbound types carry are
+/// optional.
+#[derive(Debug, Deserialize)]
+struct Outbound {
+    #[serde(rename = "type")]
+    kind: String,
+    tag: Option<String>,
+    server: Option<String>,
+    server_port: Option<u16>,
+    method: Option<String>,
+    password: Option<String>,
+    plugin: Option<String>,
+    plugin_opts: Option<String>,
+    uuid: Option<String>,
+    #[serde(default)]
+    alter_id: u32,
+    security: Option<String>,
+    flow: Option<String>,
+    username: Option<String>,
+    up_mbps: Option<u32>,
+    down_mbps: Option<u32>,
+    obfs: Option<Obfs>,
+    congestion_control: Option<String>,
+    udp_relay_mode: Option<String>,
+    private_key: Option<String>,
+    peer_public_key: Option<String>,
+    pre_shared_key: Option<String>,
+    #[serde(default)]
+    local_address: Vec<String>,
+    reserved: Option<Reserved>,
+    mtu: Option<u16>,
+    tls: Option<Tls>,
+    transport: Option<Transport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Obfs {
+    #[serde(rename = "type")]
+    kind: String,
+    password: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Tls {
+    #[serde(default)]
+    enabled: bool,
+    server_name: Option<String>,
+    #[serde(default)]
+    insecure: bool,
+    #[serde(default)]
+    alpn: Vec<String>,
+    utls: Option<Utls>,
+    reality: Option<RealityConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Utls {
+    #[serde(default)]
+    enabled: bool,
+    fingerprint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RealityConfig {
+    #[serde(default)]
+    enabled: bool,
+    public_key: String,
+    short_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transport {
+    #[serde(rename = "type")]
+    kind: String,
+    path: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    host: Vec<String>,
+    service_name: Option<String>,
+}
+
+/// Reads the proxy outbounds of a sing-box config. Outbounds that are not
+/// proxies, such as `direct` or `selector`, are left out. Proxies of a type
+/// we do not support, such as `shadowtls` or `tor`, or that lack a field are
+/// returned as skipped.
+pub fn parse_config(
+    source: &str,
+    config: serde_json::Value,
+) -> Result<(Vec<Server>, Vec<Skipped>), String> {
+    let config: Config =
+        serde_json::from_value(config).map_err(|e| format!("invalid sing-box config: {}", e))?;
+    let outbounds = match config {
+        Config::Full { outbounds } => outbounds,
+        Config::Outbounds(outbounds) => outbounds,
+    };
+    let mut servers = vec![];
+    let mut skipped = vec![];
+    for (i, outbound) in outbounds.into_iter().enumerate() {
+        let field = |key| {
+            String::from(
+                outbound
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            )
+        };
+        let (kind, tag) = (field("type"), field("tag"));
+        if matches!(
+            kind.as_str(),
+            "direct" | "block" | "dns" | "selector" | "urltest"
+        ) {
+            continue;
+        }
+        let server = serde_json::from_value::<Outbound>(outbound)
+            .map_err(|e| e.to_string())
+            .and_then(Server::try_from);
+        match server {
+            Ok(server) => servers.push(server),
+            Err(reason) => skipped.push(Skipped::entry(
+                source,
+                "outbound",
+                i + 1,
+                &kind,
+                &tag,
+                reason,
+            )),
+        }
+    }
+    Ok((servers, skipped))
+}
+
+impl Outbound {
+    fn network(&mut self) -> Option<Network> {
+        let mut transport = self.transport.take()?;
+        match transport.kind.as_str() {
+            "ws" => Some(Network::Ws {
+                path: transport.path,
+                host: transport.headers.remove("Host"),
+            }),
+            "http" => Some(Network::H2 {
+                path: transport.path,
+                host: transport.host,
+            }),
+            "grpc" => Some(Network::Grpc {
+                service_name: transport.service_name,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<Outbound> for Server {
+    type Error = String;
+
+    fn try_from(mut o: Outbound) -> Result<Self, Self::Error> {
+        let network = o.network();
+        let host = required(o.server, "server")?;
+        let port = o.server_port.ok_or("missing server_port")?;
+        let name = o.tag.unwrap_or_else(|| format!("{}:{}", host, port));
+        let tls = o.tls.unwrap_or_default();
+        let fingerprint = tls.utls.filter(|u| u.enabled).and_then(|u| u.fingerprint);
+        match o.kind.as_str() {
+            "shadowsocks" => {
+                let plugin = Plugin::from_sip003(o.plugin, o.plugin_opts)?;
+                Ok(Server::SS(ShadowSocks {
+                    name,
+                    host,
+                    port,
+                    cipher: required(o.method, "method")?,
+                    password: required(o.password, "password")?,
+                    udp: true,
+                    plugin,
+                }))
+            }
+            "vmess" => {
+                let mut vmess = Vmess {
+                    name,
+                    host,
+                    port,
+                    uuid: required(o.uuid, "uuid")?,
+                    alter_id: o.alter_id,
+                    cipher: o.security.unwrap_or_default(),
+                    tls: String::from(if tls.enabled { "tls" } else { "" }),
+                    sni: tls.server_name.unwrap_or_default(),
+                    alpn: tls.alpn.join(","),
+                    fingerprint: fingerprint.unwrap_or_default(),
+                    ..Default::default()
+                };
+                vmess.set_network(network);
+                Ok(Server::Vmess(vmess))
+            }
+            "trojan" => Ok(Server::Trojan(Trojan {
+                name,
+                host,
+                port,
+                password: required(o.password, "password")?,
+                sni: tls.server_name,
+                skip_cert_verify: tls.insecure,
+                alpn: tls.alpn,
+                network,
+            })),
+            "vless" => Ok(Server::Vless(Vless {
+                name,
+                host,
+                port,
+                uuid: required(o.uuid, "uuid")?,
+                flow: o.flow.filter(|f| !f.is_empty()),
+                tls: tls.enabled,
+                servername: tls.server_name,
+                skip_cert_verify: tls.insecure,
+                alpn: tls.alpn,
+                client_fingerprint: fingerprint,
+                reality: tls.reality.filter(|r| r.enabled).map(|r| Reality {
+                    public_key: r.public_key,
+                    short_id: r.short_id,
+                }),
+                network,
+            })),
+            "hysteria2" => Ok(Server::Hysteria2(Hysteria2 {
+                name,
+                host,
+                port,
+                password: required(o.password, "password")?,
+                sni: tls.server_name,
+                skip_cert_verify: tls.insecure,
+                obfs: o.obfs.as_ref().map(|obfs| obfs.kind.clone()),
+                obfs_password: o.obfs.and_then(|obfs| obfs.password),
+                up: o.up_mbps.map(|up| up.to_string()),
+                down: o.down_mbps.map(|down| down.to_string()),
+                alpn: tls.alpn,
+            })),
+            "tuic" => Ok(Server::Tuic(Tuic {
+                name,
+                host,
+                port,
+                uuid: required(o.uuid, "uuid")?,
+                password: required(o.password, "password")?,
+                sni: tls.server_name,
+                skip_cert_verify: tls.insecure,
+                disable_sni: false,
+                alpn: tls.alpn,
+                congestion_controller: o.congestion_control,
+                udp_relay_mode: o.udp_relay_mode,
+            })),
+            "socks" => Ok(Server::Socks5(Socks5 {
+                name,
+                host,
+                port,
+                username: o.username,
+                password: o.password,
+                tls: false,
+            })),
+            "http" => Ok(Server::Http(Http {
+                name,
+                host,
+                port,
+                username: o.username,
+                password: o.password,
+                tls: tls.enabled,
+            })),
+            "wireguard" => {
+                let (ip, ipv6) = crate::split_addresses(&o.local_address.join(","));
+                Ok(Server::WireGuard(WireGuard {
+                    name,
+                    host,
+                    port,
+                    private_key: required(o.private_key, "private_key")?,
+                    public_key: required(o.peer_public_key, "peer_public_key")?,
+                    pre_shared_key: o.pre_shared_key,
+                    ip,
+                    ipv6,
+                    mtu: o.mtu,
+                    reserved: o.reserved.map(Reserved::into_string),
+                }))
+            }
+            kind => Err(format!("unsupported sing-box outbound type {}", kind)),
+        }
+    }
+}
+
+/// Writes a server as a sing-box outbound. Fails for servers sing-box cannot
+/// express, such as ShadowsocksR ones.
+pub fn to_outbound(server: &Server) -> Result<Value, String> {
+    let mut outbound = match server {
+        Server::SS(s) => {
+            let (plugin, plugin_opts) = match &s.plugin {
+                Some(plugin) => {
+                    let (plugin, opts) = sip003(plugin)?;
+                    (Some(plugin), Some(opts))
+                }
+                None => (None, None),
+            };
+            json!({
+                "type": "shadowsocks",
+                "method": s.cipher,
+                "password": s.password,
+                "plugin": plugin,
+                "plugin_opts": plugin_opts,
+            })
+        }
+        Server::Vmess(v) => json!({
+            "type": "vmess",
+            "uuid": v.uuid,
+            "alter_id": v.alter_id,
+            "security": Some(&v.cipher).filter(|c| !c.is_empty()),
+            "tls": tls(
+                v.tls == "tls",
+                Some(&v.sni).filter(|s| !s.is_empty()),
+                false,
+                &v.alpn.split(',').filter(|a| !a.is_empty()).map(String::from).collect::<Vec<_>>(),
+                Some(&v.fingerprint).filter(|f| !f.is_empty()),
+            ),
+            "transport": transport(v.network())?,
+        }),
+        Server::Trojan(t) => json!({
+            "type": "trojan",
+            "password": t.password,
+            "tls": tls(true, t.sni.as_ref(), t.skip_cert_verify, &t.alpn, None),
+            "transport": transport(t.network.clone())?,
+        }),
+        Server::Vless(v) => {
+            let mut tls = tls(
+                v.tls,
+                v.servername.as_ref(),
+                v.skip_cert_verify,
+                &v.alpn,
+                v.client_fingerprint.as_ref(),
+            );
+            if let (Some(reality), Some(tls)) = (&v.reality, &mut tls) {
+                tls["reality"] = json!({
+                    "enabled": true,
+                    "public_key": reality.public_key,
+                    "short_id": reality.short_id,
+                });
+            }
+            json!({
+                "type": "vless",
+                "uuid": v.uuid,
+                "flow": v.flow,
+                "tls": tls,
+                "transport": transport(v.network.clone())?,
+            })
+        }
+        Server::Hysteria2(h) => json!({
+            "type": "hysteria2",
+            "password": h.password,
+            "up_mbps": h.up.as_deref().and_then(mbps),
+            "down_mbps": h.down.as_deref().and_then(mbps),
+            "obfs": h.obfs.as_ref().map(|kind| json!({
+                "type": kind,
+                "password": h.obfs_password,
+            })),
+            "tls": tls(true, h.sni.as_ref(), h.skip_cert_verify, &h.alpn, None),
+        }),
+        Server::Tuic(t) => {
+            let mut tls = tls(true, t.sni.as_ref(), t.skip_cert_verify, &t.alpn, None);
+            if let (true, Some(tls)) = (t.disable_sni, &mut tls) {
+                tls["disable_sni"] = json!(true);
+            }
+            json!({
+                "type": "tuic",
+                "uuid": t.uuid,
+                "password": t.password,
+                "congestion_control": t.congestion_controller,
+                "udp_relay_mode": t.udp_relay_mode,
+                "tls": tls,
+            })
+        }
+        Server::Socks5(s) if s.tls => return Err(String::from("sing-box has no socks over TLS")),
+        Server::Socks5(s) => json!({
+            "type": "socks",
+            "username": s.username,
+            "password": s.password,
+        }),
+        Server::Http(h) => json!({
+            "type": "http",
+            "username": h.username,
+            "password": h.password,
+            "tls": tls(h.tls, None, false, &[], None),
+        }),
+        Server::WireGuard(w) => {
+            let local_address: Vec<String> = [
+                w.ip.as_ref().map(|ip| format!("{}/32", ip)),
+                w.ipv6.as_ref().map(|ip| format!("{}/128", ip)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            // byte lists are written as such, anything else is base64
+            let reserved = w.reserved.as_ref().map(|r| {
+                match r
+                    .split(',')
+                    .map(|b| b.trim().parse())
+                    .collect::<Result<Vec<u8>, _>>()
+                {
+                    Ok(bytes) => json!(bytes),
+                    Err(_) => json!(r),
+                }
+            });
+            json!({
+                "type": "wireguard",
+                "local_address": local_address,
+                "private_key": w.private_key,
+                "peer_public_key": w.public_key,
+                "pre_shared_key": w.pre_shared_key,
+                "reserved": reserved,
+                "mtu": w.mtu,
+            })
+        }
+        Server::Ssr(_) => return Err(String::from("sing-box has no ShadowsocksR outbound")),
+    };
+    outbound["tag"] = json!(server.name());
+    outbound["server"] = json!(server.host());
+    outbound["server_port"] = json!(server.port());
+    if let Value::Object(fields) = &mut outbound {
+        fields.retain(|_, v| !v.is_null());
+    }
+    Ok(outbound)
+}
+
+fn tls(
+    enabled: bool,
+    server_name: Option<&String>,
+    insecure: bool,
+    alpn: &[String],
+    fingerprint: Option<&String>,
+) -> Option<Value> {
+    if !enabled {
+        return None;
+    }
+    let mut tls = json!({ "enabled": true });
+    if let Some(server_name) = server_name {
+        tls["server_name"] = json!(server_name);
+    }
+    if insecure {
+        tls["insecure"] = json!(true);
+    }
+    if !alpn.is_empty() {
+        tls["alpn"] = json!(alpn);
+    }
+    if let Some(fingerprint) = fingerprint {
+        tls["utls"] = json!({ "enabled": true, "fingerprint": fingerprint });
+    }
+    Some(tls)
+}
+
+fn transport(network: Option<Network>) -> Result<Option<Value>, String> {
+    let mut transport = match network {
+        None => return Ok(None),
+        Some(Network::Ws { path, host }) => json!({
+            "type": "ws",
+            "path": path,
+            "headers": host.map(|host| json!({ "Host": host })),
+        }),
+        Some(Network::H2 { path, host }) => json!({
+            "type": "http",
+            "path": path,
+            "host": host,
+        }),
+        Some(Network::Grpc { service_name }) => json!({
+            "type": "grpc",
+            "service_name": service_name,
+        }),
+        Some(Network::Http { .. }) => {
+            return Err(String::from("sing-box has no HTTP header obfuscation"))
+        }
+    };
+    if let Value::Object(fields) = &mut transport {
+        fields.retain(|_, v| !v.is_null());
+    }
+    Ok(Some(transport))
+}
+
+/// Reads a bandwidth such as `100` or `100 Mbps` as megabits per second.
+fn mbps(bandwidth: &str) -> Option<u32> {
+    let digits = bandwidth.trim_start();
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+/// Splits a ShadowSocks plugin into the SIP003 name and options sing-box
+/// takes.
+fn sip003(plugin: &Plugin) -> Result<(String, String), String> {
+    match plugin {
+        Plugin::Obfs { mode, host } => {
+            let mut opts = format!("obfs={}", mode);
+            if let Some(host) = host {
+                opts.push_str(&format!(";obfs-host={}", host));
+            }
+            Ok((String::from("obfs-local"), opts))
+        }
+        Plugin::V2ray {
+            mode,
+            host,
+            path,
+            tls,
+            mux,
+        } => {
+            let mut opts = format!("mode={}", mode);
+            if let Some(host) = host {
+                opts.push_str(&format!(";host={}", host));
+            }
+            if let Some(path) = path {
+                opts.push_str(&format!(";path={}", path));
+            }
+            if *tls {
+                opts.push_str(";tls");
+            }
+            if *mux {
+                opts.push_str(";mux=1");
+            }
+            Ok((String::from("v2ray-plugin"), opts))
+        }
+        Plugin::ShadowTls { .. } => Err(String::from(
+            "sing-box takes shadow-tls as a separate outbound, not a plugin",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(servers: &[Server]) -> Vec<String> {
+        servers.iter().map(Server::to_string).collect()
+    }
+
+    #[test]
+    fn proxy_outbounds() {
+        let config = json!({"outbounds": [
+            {"type": "direct", "tag": "direct"},
+            {"type": "vless", "tag": "v", "server": "v.example", "server_port": 443, "uuid": "u",
+             "flow": "xtls-rprx-vision",
+             "tls": {"enabled": true, "server_name": "s.example", "utls": {"enabled": true, "fingerprint": "chrome"},
+                     "reality": {"enabled": true, "public_key": "KEY", "short_id": "ab"}}},
+            {"type": "shadowsocks", "tag": "ss", "server": "s.example", "server_port": 8388,
+             "method": "aes-256-gcm", "password": "pw", "plugin": "obfs-local", "plugin_opts": "obfs=http"},
+            {"type": "wireguard", "tag": "wg", "server": "w.example", "server_port": 51820,
+             "private_key": "priv", "peer_public_key": "pub", "local_address": ["10.0.0.2/32"],
+             "reserved": "AAAA"},
+            {"type": "selector", "tag": "select", "outbounds": ["v"]}
+        ]});
+        let (servers, skipped) = parse_config("sing-box", config).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            written(&servers),
+            [
+                "{ name: 'v', type: vless, server: 'v.example', port: 443, uuid: u, udp: true, \
+                 flow: xtls-rprx-vision, tls: true, servername: s.example, client-fingerprint: chrome, \
+                 reality-opts: { public-key: KEY, short-id: 'ab' } }",
+                "{ name: 'ss', type: ss, server: 's.example', port: 8388, cipher: aes-256-gcm, password: 'pw', \
+                 udp: true, plugin: obfs, plugin-opts: { mode: http } }",
+                "{ name: 'wg', type: wireguard, server: 'w.example', port: 51820, private-key: 'priv', \
+                 public-key: 'pub', udp: true, ip: 10.0.0.2, reserved: 'AAAA' }",
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_outbounds_are_skipped() {
+        let config = json!([
+            {"type": "trojan", "tag": "t", "server": "a.example", "server_port": 443, "password": "p"},
+            {"type": "shadowtls", "tag": "st", "server": "b.example", "server_port": 443},
+            {"type": "tor", "tag": "tor"},
+            {"type": "vmess", "tag": "bad", "server": "c.example", "server_port": "x"}
+        ]);
+        let (servers, skipped) = parse_config("sing-box", config).unwrap();
+        assert_eq!(servers.len(), 1);
+        let skipped: Vec<_> = skipped
+            .iter()
+            .map(|s| (s.position.as_str(), s.protocol.as_str(), s.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("outbound 2", "shadowtls", "st"),
+                ("outbound 3", "tor", "tor"),
+                ("outbound 4", "vmess", "bad"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+
+use serde_json::Value;
+
+use crate::skipped::Skipped;
+use crate::{number_or_string, Plugin, Server, ShadowSocks};
+
+/// A SIP008 online config: `{"version": 1, "servers": [...]}`. Servers are
+/// read one by one, so that one we cannot read does not spoil the others.
+#[derive(Debug, Deserialize)]
+struct Config {
+    servers: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    remarks: Option<String>,
+    server: String,
+    #[serde(deserialize_with = "number_or_string")]
+    server_port: u16,
+    password: String,
+    method: String,
+    plugin: Option<String>,
+    plugin_opts: Option<String>,
+}
+
+/// Tells whether a JSON subscription body is a SIP008 config.
+pub fn is_config(config: &serde_json::Value) -> bool {
+    config.get("servers").is_some()
+}
+
+/// Reads the ShadowSocks servers of a SIP008 config. Servers that lack a
+/// field or use an unsupported plugin are returned as skipped.
+pub fn parse_config(
+    source: &str,
+    config: serde_json::Value,
+) -> Result<(Vec<Server>, Vec<Skipped>), String> {
+    let config: Config =
+        serde_json::from_value(config).map_err(|e| format!("invalid sip008 config: {}", e))?;
+    let mut servers = vec![];
+    let mut skipped = vec![];
+    for (i, entry) in config.servers.into_iter().enumerate() {
+        let remarks = String::from(
+            entry
+                .get("remarks")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        let server = serde_json::from_value::<Entry>(entry)
+            .map_err(|e| e.to_string())
+            .and_then(to_server);
+        match server {
+            Ok(server) => servers.push(server),
+            Err(reason) => skipped.push(Skipped::entry(
+                source,
+                "server",
+                i + 1,
+                "ss",
+                &remarks,
+                reason,
+            )),
+        }
+    }
+    Ok((servers, skipped))
+}
+
+fn to_server(e: Entry) -> Result<Server, String> {
+    let plugin = Plugin::from_sip003(e.plugin, e.plugin_opts)?;
+    Ok(Server::SS(ShadowSocks {
+        name: e
+            .remarks
+            .filter(|r| !r.is_empty())
+            .unwrap_or_else(|| format!("{}:{}", e.server, e.server_port)),
+        host: e.server,
+        port: e.server_port,
+        cipher: e.method,
+        password: e.password,
+        udp: true,
+        plugin,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn servers() {
+        let config = json!({"version": 1, "servers": [
+            {"id": "1", "remarks": "ok", "server": "a.example", "server_port": "8388",
+             "password": "pw", "method": "aes-256-gcm", "plugin": "v2ray-plugin", "plugin_opts": "tls;host=v.example"},
+            {"remarks": "", "server": "b.example", "server_port": 8388, "password": "pw", "method": "aes-256-gcm"},
+            {"remarks": "broken", "server": "c.example"}
+        ]});
+        assert!(is_config(&config));
+        let (servers, skipped) = parse_config("sip008", config).unwrap();
+        let written: Vec<String> = servers.iter().map(Server::to_string).collect();
+        assert_eq!(
+            written,
+            [
+                "{ name: 'ok', type: ss, server: 'a.example', port: 8388, cipher: aes-256-gcm, password: 'pw', \
+                 udp: true, plugin: v2ray-plugin, plugin-opts: { mode: websocket, tls: true, mux: false, host: v.example } }",
+                "{ name: 'b.example:8388', type: ss, server: 'b.example', port: 8388, cipher: aes-256-gcm, \
+                 password: 'pw', udp: true }",
+            ]
+        );
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            (skipped[0].position.as_str(), skipped[0].snippet.as_str()),
+            ("server 3", "broken")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+/// A share link or an entry of a Clash profile or JSON config that was left
+/// out because it could not be parsed.
+#[derive(Debug)]
+pub struct Skipped {
+    pub source: String,
+    /// Where it is in the subscription, such as `line 3` or `proxy 3`.
+    pub position: String,
+    /// The scheme of the link or the type of the entry, empty for lines that
+    /// are not links at all.
+    pub protocol: String,
+    pub reason: String,
+    /// The start of the link with its credentials masked, or the name of the
+    /// entry.
+    pub snippet: String,
+}
+
+impl Skipped {
+    pub fn link(source: &str, line: usize, text: &str, reason: String) -> Skipped {
+        let protocol = text.split_once("://").map_or("", |(scheme, _)| scheme);
+        Skipped {
+            source: String::from(source),
+            position: format!("line {}", line),
+            protocol: String::from(protocol),
+            reason,
+            snippet: redact(text),
+        }
+    }
+
+    /// The `n`th entry, counting from 1, of a list of `what`s such as
+    /// `proxy`. Entries keep their credentials in fields of their own, so
+    /// only the name is shown.
+    pub fn entry(
+        source: &str,
+        what: &str,
+        n: usize,
+        protocol: &str,
+        name: &str,
+        reason: String,
+    ) -> Skipped {
+        Skipped {
+            source: String::from(source),
+            position: format!("{} {}", what, n),
+            protocol: String::from(protocol),
+            reason,
+            snippet: String::from(name),
+        }
+    }
+}
+
+/// Prints the skipped links, if any, to stderr.
+pub fn print_report(skipped: &[Skipped]) {
+    if skipped.is_empty() {
+        return;
+    }
+    let entries = if skipped.len() == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    eprintln!("skipped {} unreadable {}:", skipped.len(), entries);
+    for s in skipped {
+        let protocol = if s.protocol.is_empty() {
+            "-"
+        } else {
+            &s.protocol
+        };
+        eprintln!(
+            "  {} {}  {}  {}  {}",
+            s.source, s.position, protocol, s.reason, s.snippet
+        );
+    }
+}
+
+/// Shortens a link so it can be shown without leaking credentials. The
+/// userinfo is masked and the query, which may carry passwords, left out.
+/// Base64 bodies, which hold the credentials anywhere, are cut short.
+fn redact(text: &str) -> String {
+    const KEEP: usize = 12;
+    let shorten = |s: &str| match s.char_indices().nth(KEEP) {
+        Some((i, _)) => format!("{}…", &s[..i]),
+        None => String::from(s),
+    };
+    let (scheme, body) = match text.split_once("://") {
+        Some(parts) => parts,
+        None => return shorten(text),
+    };
+    let (main, name) = match body.split_once('#') {
+        Some((main, name)) => (main, Some(name)),
+        None => (body, None),
+    };
+    let main = main.split('?').next().unwrap_or_default();
+    let main = match main.rsplit_once('@') {
+        Some((_, server)) => format!("***@{}", server),
+        None if matches!(scheme, "vmess" | "ssr" | "ss") => shorten(main),
+        None => String::from(main),
+    };
+    match name {
+        Some(name) => format!("{}://{}#{}", scheme, main, name),
+        None => format!("{}://{}", scheme, main),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_masks_credentials() {
+        assert_eq!(
+            redact("trojan://secret@t.example.com:443?sni=s.example.com&pw=x#Name"),
+            "trojan://***@t.example.com:443#Name"
+        );
+        assert_eq!(
+            redact("vmess://eyJhZGQiOiJ2bS5leGFtcGxlLmNvbSJ9"),
+            "vmess://eyJhZGQiOiJ2…"
+        );
+        assert_eq!(
+            redact("socks5://s.example.com:1080"),
+            "socks5://s.example.com:1080"
+        );
+        assert_eq!(redact("not a link at all"), "not a link a…");
+    }
+
+    #[test]
+    fn link() {
+        let s = Skipped::link("sub", 3, "snell://x@h:1", String::from("unsupported"));
+        assert_eq!(s.position, "line 3");
+        assert_eq!(s.protocol, "snell");
+        assert_eq!(s.snippet, "snell://***@h:1");
+        assert_eq!(Skipped::link("sub", 1, "junk", String::new()).protocol, "");
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use hyper::body::Bytes;
+use hyper::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use hyper::{StatusCode, Uri};
+
+use crate::cache::{Cache, Meta};
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
+use crate::userinfo::UserInfo;
+
+/// A subscription to fetch servers from. Its name tags the servers it
+/// provides.
+#[derive(Debug)]
+pub struct Subscription {
+    pub name: String,
+    pub location: Location,
+}
+
+/// The body of a subscription, with the quota its provider reported if any.
+#[derive(Debug)]
+pub struct Fetched {
+    pub body: Bytes,
+    pub userinfo: Option<UserInfo>,
+    /// The provider could not be reached and the body and quota are those
+    /// of the cached copy.
+    pub stale: bool,
+    /// The headers to cache the body with, see [`Subscription::keep`].
+    meta: Option<Meta>,
+}
+
+/// Where a subscription is read from.
+#[derive(Debug)]
+pub enum Location {
+    /// An HTTP(S) URL served by a provider.
+    Http(Uri),
+    /// A local file, given as `file://path` or a bare path, such as an
+    /// archived snapshot.
+    File(PathBuf),
+    /// Standard input, given as `-`.
+    Stdin,
+}
+
+impl Subscription {
+    pub async fn fetch(&self, fetcher: &Fetcher, cache: &Cache) -> Result<Fetched> {
+        match &self.location {
+            Location::Http(uri) => self.fetch_http(fetcher, uri, cache).await,
+            Location::File(path) => Ok(Fetched {
+                body: std::fs::read(path).map_err(Error::io(path))?.into(),
+                userinfo: None,
+                stale: false,
+                meta: None,
+            }),
+            Location::Stdin => {
+                let mut body = vec![];
+                std::io::stdin()
+                    .read_to_end(&mut body)
+                    .map_err(Error::io("<stdin>"))?;
+                Ok(Fetched {
+                    body: body.into(),
+                    userinfo: None,
+                    stale: false,
+                    meta: None,
+                })
+            }
+        }
+    }
+
+    /// Caches the body of a fresh download. Only call this once the body is
+    /// known to hold servers, so that an error page served with a 200 never
+    /// replaces the last good copy.
+    pub fn keep(&self, cache: &Cache, fetched: &Fetched) {
+        if let (Location::Http(uri), Some(meta)) = (&self.location, &fetched.meta) {
+            if let Err(e) = cache.store(uri, meta, &fetched.body) {
+                eprintln!("warning: {}: cannot cache: {}", self.name, e);
+            }
+        }
+    }
+
+    /// Downloads an HTTP subscription, revalidating the cached copy if there
+    /// is one. The cached copy is also used, with a warning, when the provider
+    /// cannot be reached or answers with an error.
+    async fn fetch_http(&self, fetcher: &Fetcher, uri: &Uri, cache: &Cache) -> Result<Fetched> {
+        let cached = cache.load(uri);
+        let mut headers = vec![];
+        if let Some((meta, _)) = &cached {
+            if let Some(etag) = &meta.etag {
+                headers.push((IF_NONE_MATCH, etag.clone()));
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                headers.push((IF_MODIFIED_SINCE, last_modified.clone()));
+            }
+        }
+        let error = match fetcher.get(uri, &headers).await {
+            Ok(resp) if resp.status == StatusCode::NOT_MODIFIED => match cached {
+                Some((mut meta, body)) => {
+                    // the quota changes even when the servers do not
+                    match header(&resp.headers, "subscription-userinfo") {
+                        Some(userinfo) => {
+                            meta.userinfo = Some(userinfo);
+                            return Ok(self.fetched(meta, body, false, true));
+                        }
+                        None => return Ok(self.fetched(meta, body, false, false)),
+                    }
+                }
+                None => String::from("304 Not Modified without a cached copy"),
+            },
+            Ok(resp) if resp.status.is_success() && resp.body.is_empty() => {
+                String::from("empty response")
+            }
+            Ok(resp) if resp.status.is_success() => {
+                let meta = Meta {
+                    etag: header(&resp.headers, ETAG.as_str()),
+                    last_modified: header(&resp.headers, LAST_MODIFIED.as_str()),
+                    userinfo: header(&resp.headers, "subscription-userinfo"),
+                };
+                return Ok(self.fetched(meta, resp.body, false, true));
+            }
+            Ok(resp) => format!("HTTP {}", resp.status),
+            Err(e) => e,
+        };
+        match cached {
+            Some((meta, body)) => {
+                eprintln!("warning: {}: {}, using the cached copy", self.name, error);
+                Ok(self.fetched(meta, body, true, false))
+            }
+            None => Err(Error::Fetch {
+                source: self.name.clone(),
+                reason: error,
+            }),
+        }
+    }
+
+    /// `store` tells whether the body should be cached with `meta` by
+    /// [`Subscription::keep`].
+    fn fetched(&self, meta: Meta, body: Bytes, stale: bool, store: bool) -> Fetched {
+        let userinfo = meta.userinfo.as_ref().and_then(|v| match v.parse() {
+            Ok(userinfo) => Some(userinfo),
+            Err(e) => {
+                eprintln!("{}: ignoring subscription-userinfo: {}", self.name, e);
+                None
+            }
+        });
+        Fetched {
+            body,
+            userinfo,
+            stale,
+            meta: Some(meta).filter(|_| store),
+        }
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+impl std::str::FromStr for Subscription {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // a `=` before the scheme separates the name, later ones belong to the query
+        let (name, location) = match s.split_once('=') {
+            Some((name, location)) if !name.contains("://") => (Some(name), location),
+            _ => (None, s),
+        };
+        let location = match location.split_once("://") {
+            _ if location == "-" => Location::Stdin,
+            Some(("file", path)) => {
+                let path = urlencoding::decode(path).map_err(|e| e.to_string())?;
+                Location::File(PathBuf::from(path.as_ref()))
+            }
+            Some(("http" | "https", _)) => {
+                let uri: Uri = location
+                    .parse()
+                    .map_err(|e| format!("invalid subscription {}: {}", s, e))?;
+                Location::Http(uri)
+            }
+            Some((scheme, _)) => {
+                return Err(format!(
+                    "invalid subscription {}: unsupported scheme {}",
+                    s, scheme
+                ))
+            }
+            // a bare path, as `convert` takes it
+            None => Location::File(PathBuf::from(location)),
+        };
+        let name = match (name, &location) {
+            (Some(name), _) => String::from(name),
+            (None, Location::Http(uri)) => String::from(uri.host().unwrap_or_default()),
+            (None, Location::File(path)) => path
+                .file_stem()
+                .map_or_else(String::new, |s| s.to_string_lossy().into_owned()),
+            (None, Location::Stdin) => String::from("stdin"),
+        };
+        Ok(Subscription { name, location })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse(s: &str) -> (String, Location) {
+        let subscription: Subscription = s.parse().unwrap();
+        (subscription.name, subscription.location)
+    }
+
+    #[test]
+    fn http() {
+        let (name, location) = parse("https://sub.example.com/api?token=a=b");
+        assert_eq!(name, "sub.example.com");
+        assert!(matches!(location, Location::Http(uri) if uri.query() == Some("token=a=b")));
+        let (name, location) = parse("main=http://sub.example.com/x");
+        assert_eq!(name, "main");
+        assert!(matches!(location, Location::Http(_)));
+    }
+
+    #[test]
+    fn files() {
+        let (name, location) = parse("backup=file:///home/me/my%20backup.txt");
+        assert_eq!(name, "backup");
+        assert!(matches!(location, Location::File(p) if p == Path::new("/home/me/my backup.txt")));
+        let (name, location) = parse("b=sub.txt");
+        assert_eq!(name, "b");
+        assert!(matches!(location, Location::File(p) if p == Path::new("sub.txt")));
+        let (name, location) = parse("saved/sub.txt");
+        assert_eq!(name, "sub");
+        assert!(matches!(location, Location::File(p) if p == Path::new("saved/sub.txt")));
+    }
+
+    #[test]
+    fn stdin() {
+        let (name, location) = parse("-");
+        assert_eq!(name, "stdin");
+        assert!(matches!(location, Location::Stdin));
+    }
+
+    #[test]
+    fn unsupported_scheme() {
+        assert!("ftp://sub.example.com/x".parse::<Subscription>().is_err());
+    }
+}
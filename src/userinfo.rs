@@ -0,0 +1,159 @@
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Traffic quota and expiry a provider reports in the
+/// `subscription-userinfo` response header, e.g.
+/// `upload=455727941; download=6174315083; total=1073741824000; expire=1671815872`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UserInfo {
+    pub upload: u64,
+    pub download: u64,
+    pub total: u64,
+    /// Unix timestamp of the end of the plan, absent for plans without one.
+    pub expire: Option<u64>,
+}
+
+impl FromStr for UserInfo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut info = UserInfo::default();
+        for field in s.split(';').map(str::trim).filter(|f| !f.is_empty()) {
+            let (k, v) = field
+                .split_once('=')
+                .ok_or_else(|| format!("malformed userinfo field {}", field))?;
+            let v = v.trim();
+            // some providers send floats or leave the expiry empty
+            let n = || {
+                v.parse::<f64>()
+                    .map(|n| n as u64)
+                    .map_err(|_| format!("invalid userinfo value {}", field))
+            };
+            match k.trim() {
+                "upload" => info.upload = n()?,
+                "download" => info.download = n()?,
+                "total" => info.total = n()?,
+                "expire" if v.is_empty() || v == "0" => info.expire = None,
+                "expire" => info.expire = Some(n()?),
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+impl UserInfo {
+    pub fn used(&self) -> u64 {
+        self.upload.saturating_add(self.download)
+    }
+
+    /// A summary that only depends on the header, suitable for writing into
+    /// the profile, e.g. `6.2 GiB of 1000.0 GiB used, expires 2022-12-23`.
+    pub fn summary(&self) -> String {
+        let mut s = format!(
+            "{} of {} used",
+            format_bytes(self.used()),
+            format_bytes(self.total)
+        );
+        if let Some(expire) = self.expire {
+            s.push_str(&format!(", expires {}", format_date(expire)));
+        }
+        s
+    }
+
+    /// [`UserInfo::summary`] plus what is left as of now.
+    pub fn report(&self) -> String {
+        let mut s = self.summary();
+        if self.total > 0 {
+            let left = self.total.saturating_sub(self.used());
+            s.push_str(&format!(
+                "; {} ({}%) left",
+                format_bytes(left),
+                u128::from(left) * 100 / u128::from(self.total)
+            ));
+        }
+        if let Some(expire) = self.expire {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            match expire.checked_sub(now) {
+                Some(secs) => s.push_str(&format!(", {} days to go", secs / 86400)),
+                None => s.push_str(", expired"),
+            }
+        }
+        s
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats a unix timestamp as a UTC `YYYY-MM-DD` date.
+fn format_date(timestamp: u64) -> String {
+    // Howard Hinnant's days-to-civil algorithm
+    let z = (timestamp / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let info: UserInfo =
+            "upload=455727941; download=6174315083; total=1073741824000; expire=1671815872"
+                .parse()
+                .unwrap();
+        assert_eq!(info.used(), 6630043024);
+        assert_eq!(
+            info.summary(),
+            "6.2 GiB of 1000.0 GiB used, expires 2022-12-23"
+        );
+    }
+
+    #[test]
+    fn lenient_values() {
+        let info: UserInfo = "upload=1.5e3;download=512;expire=0;".parse().unwrap();
+        assert_eq!((info.upload, info.download, info.expire), (1500, 512, None));
+        assert!("upload".parse::<UserInfo>().is_err());
+    }
+
+    #[test]
+    fn huge_values_do_not_overflow() {
+        let info = UserInfo {
+            upload: u64::MAX,
+            download: u64::MAX,
+            total: u64::MAX,
+            expire: None,
+        };
+        assert_eq!(info.used(), u64::MAX);
+        assert!(info.report().ends_with("; 0 B (0%) left"));
+        let info = UserInfo {
+            upload: 0,
+            download: 0,
+            total: u64::MAX,
+            expire: None,
+        };
+        assert!(info.report().ends_with("(100%) left"));
+    }
+}